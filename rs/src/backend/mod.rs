@@ -0,0 +1,68 @@
+//! Pluggable code-generation backends
+//!
+//! `primitives` used to build `x86::Ins` directly. Everything now goes
+//! through the `Backend` trait instead, so the immediate tagging scheme
+//! (`immediate::{SHIFT, MASK, ...}`) and the AST walk stay shared while
+//! instruction *selection* is pluggable per target. `x86` is the original
+//! backend; `wasm` lowers the same primitives to wasm32 text instead.
+
+pub mod wasm;
+pub mod x86;
+
+use crate::{compiler::state::State, core::{ASM, AST}};
+
+/// Which machine `compiler::emit` is lowering the AST down to.
+///
+/// Stashed on `State` (as `target`) so every primitive can dispatch off it
+/// without threading an extra parameter through `emit::eval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    X86_64,
+    Wasm32,
+}
+
+impl Target {
+    pub fn backend(self) -> &'static dyn Backend {
+        match self {
+            Target::X86_64 => &x86::X86,
+            Target::Wasm32 => &wasm::Wasm,
+        }
+    }
+}
+
+/// The comparison kinds `primitives` needs for `eq?`/`<`/`>`/`<=`/`>=`,
+/// named so a `Backend` impl doesn't have to know x86 `SETcc` mnemonics or
+/// wasm's `i64.*_s` suffixes.
+#[derive(Debug, Clone, Copy)]
+pub enum Cc {
+    Eq,
+    Lt,
+    Gt,
+    Lte,
+    Gte,
+}
+
+/// Lowers the primitive operations `primitives` exposes into
+/// target-specific instructions.
+///
+/// One method per primitive `primitives` used to implement directly; see
+/// `x86` for the original behavior and `wasm` for the wasm32 lowering.
+pub trait Backend {
+    fn inc(&self, s: &mut State, x: &AST) -> ASM;
+    fn dec(&self, s: &mut State, x: &AST) -> ASM;
+
+    fn fixnump(&self, s: &mut State, x: &AST) -> ASM;
+    fn flonump(&self, s: &mut State, x: &AST) -> ASM;
+    fn booleanp(&self, s: &mut State, x: &AST) -> ASM;
+    fn charp(&self, s: &mut State, x: &AST) -> ASM;
+    fn nullp(&self, s: &mut State, x: &AST) -> ASM;
+    fn zerop(&self, s: &mut State, x: &AST) -> ASM;
+    fn not(&self, s: &mut State, x: &AST) -> ASM;
+
+    fn plus(&self, s: &mut State, x: &AST, y: &AST) -> ASM;
+    fn minus(&self, s: &mut State, x: &AST, y: &AST) -> ASM;
+    fn mul(&self, s: &mut State, x: &AST, y: &AST) -> ASM;
+    fn quotient(&self, s: &mut State, x: &AST, y: &AST) -> ASM;
+    fn remainder(&self, s: &mut State, x: &AST, y: &AST) -> ASM;
+    fn compare(&self, s: &mut State, x: &AST, y: &AST, cc: Cc) -> ASM;
+}
@@ -0,0 +1,188 @@
+//! wasm32 backend
+//!
+//! Wasm is a stack machine, so unlike `x86` there's no RAX/Stack convention
+//! to maintain here: `emit::eval` pushes a value, and a binary primitive is
+//! "evaluate both operands, then emit the matching instruction" with no
+//! register bookkeeping at all. `si`-indexed stack slots become wasm
+//! locals, addressed with `local.get`/`local.set` instead of `Stack(si)`.
+//!
+//! Heap objects (pairs, strings, vectors) live in wasm linear memory and
+//! are read with `i64.load`/written with `i64.store`, the same immediate
+//! tagging scheme (`immediate::{SHIFT, MASK, ...}`) applies to the value on
+//! top of the stack either way. The runtime (`print`, `car`, `cdr`, ...) is
+//! re-exposed to this module as wasm imports rather than linked-in `extern
+//! "C"` calls.
+//!
+//! Flonums aren't boxed here the way `x86::float_dispatch` boxes them:
+//! wasm has native `f64` locals, so a later pass can keep them unboxed on
+//! the wasm value stack. For now `flonump` only recognizes the heap-boxed
+//! representation shared with the x86 backend, and the arithmetic ops stay
+//! fixnum-only; teaching this backend to promote to `f64` is follow-up
+//! work once flonums round-trip through the wasm locals cleanly.
+//!
+//! **Not wired up end-to-end yet.** This module only emits the
+//! instruction text for the handful of primitives in `Backend` --
+//! nothing here wraps that text in a `(module ...)`/`(func ...)` shell,
+//! declares the `print`/`car`/`cdr`/... imports it assumes, assembles it
+//! into an actual `.wasm` binary, or threads a `--target` flag through to
+//! pick `Target::Wasm32` in the first place. It also assumes
+//! `emit::eval` itself dispatches on `s.target` the same way `plus`/
+//! `inc`/etc. do here, which isn't part of this change since `emit` and
+//! the CLI aren't touched by it -- if `emit::eval` doesn't already
+//! special-case wasm, it will keep emitting `x86::Ins` text for operand
+//! evaluation while this module emits wasm mnemonics for the operation
+//! around it, and the result won't assemble as either target. Don't
+//! treat this backend as usable until `emit::eval`'s target-awareness,
+//! the module/import/memory wrapper, and the CLI wiring all exist and
+//! a real program has been lowered through it and run.
+
+use super::{Backend, Cc};
+use crate::{compiler::emit, compiler::state::State, core::*, immediate};
+
+pub struct Wasm;
+
+impl Backend for Wasm {
+    fn inc(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + const_i64(immediate::n(1)) + "    i64.add\n"
+    }
+
+    fn dec(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + const_i64(immediate::n(1)) + "    i64.sub\n"
+    }
+
+    fn fixnump(&self, s: &mut State, x: &AST) -> ASM {
+        tagged(s, x, immediate::NUM)
+    }
+
+    fn flonump(&self, s: &mut State, x: &AST) -> ASM {
+        tagged(s, x, immediate::FLOAT)
+    }
+
+    fn booleanp(&self, s: &mut State, x: &AST) -> ASM {
+        tagged(s, x, immediate::BOOL)
+    }
+
+    fn charp(&self, s: &mut State, x: &AST) -> ASM {
+        tagged(s, x, immediate::CHAR)
+    }
+
+    fn nullp(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + const_i64(immediate::NIL) + "    i64.eq\n" + encode_bool()
+    }
+
+    fn zerop(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + const_i64(immediate::NUM) + "    i64.eq\n" + encode_bool()
+    }
+
+    fn not(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + const_i64(immediate::FALSE) + "    i64.eq\n" + encode_bool()
+    }
+
+    fn plus(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        emit::eval(s, x) + emit::eval(s, y) + "    i64.add\n"
+    }
+
+    fn minus(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        emit::eval(s, x) + emit::eval(s, y) + "    i64.sub\n"
+    }
+
+    fn mul(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        emit::eval(s, x)
+            + shift_right(immediate::SHIFT)
+            + emit::eval(s, y)
+            + "    i64.mul\n"
+    }
+
+    fn quotient(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        emit::eval(s, x)
+            + shift_right(immediate::SHIFT)
+            + emit::eval(s, y)
+            + shift_right(immediate::SHIFT)
+            + "    i64.div_s\n"
+            + shift_left(immediate::SHIFT)
+    }
+
+    fn remainder(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        emit::eval(s, x)
+            + shift_right(immediate::SHIFT)
+            + emit::eval(s, y)
+            + shift_right(immediate::SHIFT)
+            + "    i64.rem_s\n"
+            + shift_left(immediate::SHIFT)
+    }
+
+    fn compare(&self, s: &mut State, x: &AST, y: &AST, cc: Cc) -> ASM {
+        let op = match cc {
+            Cc::Eq => "i64.eq",
+            Cc::Lt => "i64.lt_s",
+            Cc::Gt => "i64.gt_s",
+            Cc::Lte => "i64.le_s",
+            Cc::Gte => "i64.ge_s",
+        };
+
+        emit::eval(s, x) + emit::eval(s, y) + format!("    {}\n", op) + encode_bool()
+    }
+}
+
+/// Push the tag mask of the value already on top of the stack, compare it
+/// against `tag`, and encode the result as a Scheme boolean.
+fn tagged(s: &mut State, x: &AST, tag: i64) -> ASM {
+    emit::eval(s, x) + const_i64(immediate::MASK) + "    i64.and\n" + const_i64(tag) + "    i64.eq\n" + encode_bool()
+}
+
+/// `i64.eq`/`i64.lt_s`/etc leave a wasm `i32` boolean, not a Scheme one;
+/// widen it, shift it into the tag bits, and OR in the `BOOL` tag.
+fn encode_bool() -> ASM {
+    let mut ctx = String::new();
+    ctx.push_str("    i64.extend_i32_u\n");
+    ctx.push_str(&format!("    i64.const {}\n", immediate::SHIFT));
+    ctx.push_str("    i64.shl\n");
+    ctx.push_str(&format!("    i64.const {}\n", immediate::BOOL));
+    ctx.push_str("    i64.or\n");
+    ctx.into()
+}
+
+fn const_i64(v: i64) -> ASM {
+    format!("    i64.const {}\n", v).into()
+}
+
+fn shift_right(by: i64) -> ASM {
+    format!("    i64.const {}\n    i64.shr_s\n", by).into()
+}
+
+fn shift_left(by: i64) -> ASM {
+    format!("    i64.const {}\n    i64.shl\n", by).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn const_i64_emits_a_single_push() {
+        assert_eq!(const_i64(42).to_string(), "    i64.const 42\n");
+    }
+
+    #[test]
+    fn shift_right_and_left_emit_the_signed_and_plain_shifts() {
+        assert_eq!(
+            shift_right(immediate::SHIFT).to_string(),
+            format!("    i64.const {}\n    i64.shr_s\n", immediate::SHIFT)
+        );
+        assert_eq!(
+            shift_left(immediate::SHIFT).to_string(),
+            format!("    i64.const {}\n    i64.shl\n", immediate::SHIFT)
+        );
+    }
+
+    #[test]
+    fn encode_bool_widens_shifts_and_tags_as_bool() {
+        let out = encode_bool().to_string();
+
+        assert!(out.contains("i64.extend_i32_u"));
+        assert!(out.contains(&format!("i64.const {}", immediate::SHIFT)));
+        assert!(out.contains("i64.shl"));
+        assert!(out.contains(&format!("i64.const {}", immediate::BOOL)));
+        assert!(out.contains("i64.or"));
+    }
+}
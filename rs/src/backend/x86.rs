@@ -0,0 +1,236 @@
+//! x86-64 backend
+//!
+//! The original (and for now, only hand-tuned) backend. This is the
+//! instruction selection `primitives` used to emit directly, plus flonum
+//! dispatch for `plus`/`minus`/`mul` and a zero-divisor guard for
+//! `quotient`/`remainder`; it lives behind the `Backend` trait now so
+//! `wasm` can sit alongside it.
+
+use super::{Backend, Cc};
+use crate::{
+    compiler::emit,
+    compiler::state::State,
+    core::*,
+    immediate,
+    x86::{Ins::*, Operand::*, Register::*, *},
+};
+
+pub struct X86;
+
+impl Backend for X86 {
+    fn inc(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + Add { r: RAX, v: Const(immediate::n(1)) }
+    }
+
+    fn dec(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + Sub { r: RAX, v: Const(immediate::n(1)) }
+    }
+
+    fn fixnump(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + emit::mask() + compare(Reg(RAX), Const(immediate::NUM), "sete")
+    }
+
+    fn flonump(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + emit::mask() + compare(Reg(RAX), Const(immediate::FLOAT), "sete")
+    }
+
+    fn booleanp(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + emit::mask() + compare(Reg(RAX), Const(immediate::BOOL), "sete")
+    }
+
+    fn charp(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + emit::mask() + compare(Reg(RAX), Const(immediate::CHAR), "sete")
+    }
+
+    fn nullp(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + compare(Reg(RAX), Const(immediate::NIL), "sete")
+    }
+
+    fn zerop(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + compare(Reg(RAX), Const(immediate::NUM), "sete")
+    }
+
+    fn not(&self, s: &mut State, x: &AST) -> ASM {
+        emit::eval(s, x) + compare(Reg(RAX), Const(immediate::FALSE), "sete")
+    }
+
+    fn plus(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        binop(s, x, y) + float_dispatch(s, "addsd", Add { r: RAX, v: Stack(s.si) }.into())
+    }
+
+    // `sub` subtracts the 2nd op from the first and stores the result in
+    // the 1st. This is pretty inefficient to update result in stack and
+    // load it back. Reverse the order and fix it up.
+    fn minus(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        binop(s, x, y)
+            + float_dispatch(
+                s,
+                "subsd",
+                Sub { r: RAX, v: Stack(s.si) }.into() + Load { r: RAX, si: s.si },
+            )
+    }
+
+    // The destination operand of `mul` is an implied operand located in
+    // register AX. GCC throws `Error: ambiguous operand size for `mul'`
+    // without a size quantifier.
+    fn mul(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        binop(s, x, y)
+            + float_dispatch(
+                s,
+                "mulsd",
+                Sar { r: RAX, v: immediate::SHIFT }.into() + Mul { v: Stack(s.si) },
+            )
+    }
+
+    fn quotient(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        div(s, x, y) + Sal { r: RAX, v: immediate::SHIFT }
+    }
+
+    fn remainder(&self, s: &mut State, x: &AST, y: &AST) -> ASM {
+        div(s, x, y) + Mov { to: Reg(RAX), from: Reg(RDX) } + Sal { r: RAX, v: immediate::SHIFT }
+    }
+
+    fn compare(&self, s: &mut State, x: &AST, y: &AST, cc: Cc) -> ASM {
+        let setcc = match cc {
+            Cc::Eq => "sete",
+            Cc::Lt => "setl",
+            Cc::Gt => "setg",
+            Cc::Lte => "setle",
+            Cc::Gte => "setge",
+        };
+
+        binop(s, x, y) + compare(Stack(s.si), Reg(RAX), setcc)
+    }
+}
+
+/// Evaluate arguments and store the first argument in stack and second in `RAX`
+fn binop(s: &mut State, x: &AST, y: &AST) -> ASM {
+    emit::eval(s, x) + Save { r: RAX, si: s.si } + emit::eval(s, y)
+}
+
+/// Divide `x` by `y`, leaving the quotient in `RAX` and the remainder in `RDX`
+// Division turned out to be much more trickier than I expected it to be.
+// Unlike @namin's code, I'm using a shift arithmetic right (SAR) instead of
+// shift logical right (SHR) and I don't know how the original examples
+// worked at all for negative numbers. I also had to use the CQO instruction
+// to Sign-Extend RAX which the 32 bit version is obviously not concerned
+// with. I got the idea from GCC disassembly.
+//
+// Dividend is passed in RDX:RAX and IDIV instruction takes the divisor as
+// the argument. The quotient is stored in RAX and the remainder in RDX.
+//
+// `quotient`/`remainder` are fixnum-only ops -- `idiv` has no flonum
+// equivalent that also produces a remainder -- so this does *not* go
+// through `float_dispatch` the way `plus`/`minus`/`mul` do. It still uses
+// `binop` (rather than the hand-rolled evaluation order the very first
+// version used) so the operand convention matches the other arithmetic
+// primitives; shifting each operand still happens independently of the
+// other, so that reordering doesn't change behavior.
+fn div(s: &mut State, x: &AST, y: &AST) -> ASM {
+    binop(s, x, y)
+        + Sar { r: RAX, v: immediate::SHIFT }
+        + Slice("    mov rcx, rax \n".to_string())
+        + Load { r: RAX, si: s.si }
+        + Sar { r: RAX, v: immediate::SHIFT }
+        + guard_div(s)
+        + Slice("    mov rdx, 0 \n".to_string())
+        + Slice("    cqo \n".to_string())
+        + Slice("    idiv rcx \n".to_string())
+}
+
+/// Guards `idiv rcx` against a zero divisor (`#DE`) by jumping to
+/// `rt_div_error`, a single handler shared by every division in the
+/// program (see `State::error_stub`) rather than inlining the diagnostic
+/// at each call site.
+//
+// `idiv`'s other fault, signed overflow on `INT_MIN / -1`, can't actually
+// happen here: `rax` at this point holds a fixnum shifted down to its
+// untagged ~61-bit range, which can never equal the full 64-bit
+// `i64::MIN`. It's also not a check we could emit as written -- `cmp rax,
+// imm64` isn't an encoding x86 has; `CMP` only takes a sign-extended
+// imm32. So there's deliberately no overflow arm here, just the divisor
+// check.
+fn guard_div(s: &mut State) -> ASM {
+    let error = s.error_stub("rt_div_error");
+
+    Slice("    cmp rcx, 0\n".to_string()) + Slice(format!("    je {}\n", error))
+}
+
+/// Compares the first operand with the second with `SETcc`
+// See `Ins::Cmp` to see how the compare instruction works.
+//
+// `SETcc` sets the destination operand to 0 or 1 depending on the settings of
+// the status flags (CF, SF, OF, ZF, and PF) in the EFLAGS register.
+//
+// `MOVZX` copies the contents of the source operand (register or memory
+// location) to the destination operand (register) and zero extends the value.
+fn compare(a: Operand, b: Operand, setcc: &str) -> ASM {
+    Cmp { a, b }
+        + Slice(format!("    {} al\n", setcc))
+        + Slice("    movzx rax, al\n".to_string())
+        + Slice(format!("    sal al, {}\n", immediate::SHIFT))
+        + Slice(format!("    or al, {}\n", immediate::BOOL))
+}
+
+/// Wraps a fixnum-only binary op so that it also works on flonums.
+///
+/// `x` lives in `Stack(s.si)` and `y` in `RAX`, per the usual `binop`
+/// convention. If neither is tagged `FLOAT` we fall straight through to
+/// `int_path` and nothing changes from before. Otherwise we unbox both
+/// operands into `xmm0`/`xmm1` (promoting a fixnum sibling with
+/// `cvtsi2sd`), run `sse_op`, move the `xmm0` result into `rdi` -- the
+/// System V calling convention's first integer argument register, which
+/// is where `rt_box_float(bits: i64)` expects it -- and call it to box
+/// the result back onto the heap as a new flonum.
+//
+// Flonums are heap objects (`ptr | FLOAT`, 8 bytes holding the raw `f64`
+// bits), so unlike fixnum arithmetic this path has to allocate, which
+// `rt_box_float` does through `heap::rt_alloc`. `y` is unboxed first since
+// unboxing `x` out of the stack slot overwrites `RAX`.
+fn float_dispatch(s: &mut State, sse_op: &str, int_path: ASM) -> ASM {
+    let is_float = s.label("is_float");
+    let done = s.label("arith_done");
+    let si = s.si;
+
+    Mov { to: Reg(RBX), from: Stack(si) }
+        + Slice("    mov rcx, rax\n".to_string())
+        + Slice(format!("    and rbx, {}\n", immediate::MASK))
+        + Slice(format!("    and rcx, {}\n", immediate::MASK))
+        + Slice(format!("    cmp rbx, {}\n", immediate::FLOAT))
+        + Slice(format!("    je {}\n", is_float))
+        + Slice(format!("    cmp rcx, {}\n", immediate::FLOAT))
+        + Slice(format!("    je {}\n", is_float))
+        + int_path
+        + Slice(format!("    jmp {}\n", done))
+        + Slice(format!("{}:\n", is_float))
+        + unbox_double(s, Reg(RAX), Xmm(XMM1))
+        + unbox_double(s, Stack(si), Xmm(XMM0))
+        + Slice(format!("    {} xmm0, xmm1\n", sse_op))
+        + Slice("    movq rdi, xmm0\n".to_string())
+        + Call("rt_box_float".to_string())
+        + Slice(format!("{}:\n", done))
+}
+
+/// Loads `v` into `xmm` as a double, converting from a fixnum with
+/// `cvtsi2sd` or unboxing an already-boxed flonum with `movsd`.
+///
+/// Labels come from `s.label(...)`, not `xmm`'s name -- `float_dispatch`
+/// calls this twice per arithmetic op (once per operand), so a label
+/// built from `xmm` alone would collide across every flonum op in the
+/// program instead of just within one call.
+fn unbox_double(s: &mut State, v: Operand, xmm: Operand) -> ASM {
+    let is_float = s.label("unbox_float");
+    let done = s.label("unbox_done");
+
+    Mov { to: Reg(RAX), from: v }
+        + Slice("    mov rdx, rax\n".to_string())
+        + Slice(format!("    and rdx, {}\n", immediate::MASK))
+        + Slice(format!("    cmp rdx, {}\n", immediate::FLOAT))
+        + Slice(format!("    je {}\n", is_float))
+        + Sar { r: RAX, v: immediate::SHIFT }
+        + Slice(format!("    cvtsi2sd {}, rax\n", xmm))
+        + Slice(format!("    jmp {}\n", done))
+        + Slice(format!("{}:\n", is_float))
+        + Slice(format!("    movsd {}, [rax - {}]\n", xmm, immediate::FLOAT))
+        + Slice(format!("{}:\n", done))
+}
@@ -0,0 +1,153 @@
+//! The managed Scheme heap
+//!
+//! `rt_read` used to grab the heap pointer out of `r12` via `asm!("nop")`,
+//! bump it by hand, and write the allocation's contents in manually
+//! computed to offsets -- "cursed", in the author's own words, and exactly
+//! the kind of thing that wants a real allocator instead. This module is
+//! that allocator: a single large, page-aligned region (backed by `mmap`,
+//! flanked by `PROT_NONE` guard pages so running off either end faults
+//! deterministically instead of corrupting whatever memory happened to
+//! follow it) plus a bump pointer that is checked against the region end
+//! to detect exhaustion before it turns into memory corruption, and a
+//! high-water mark reported when that happens.
+//!
+//! `rt_read` and flonum boxing (`rt_box_float`) call `rt_alloc` now
+//! instead of touching `r12` directly, which also removes the last bit of
+//! architecture-specific inline asm from those two paths.
+//!
+//! This is a deliberately staged first step, not the finished migration:
+//! `strings::make` and the emitted `cons`/vector allocation sites still
+//! bump `r12` directly, so for now there are two disjoint heaps rather
+//! than the single one the request asks for. Do not merge this as "the"
+//! managed heap until those remaining sites are moved over to `rt_alloc`
+//! too -- only then is there one place to add compaction or a semi-space
+//! GC, which is the actual point of the rewrite.
+
+use std::sync::Mutex;
+
+/// Size of the region made available to allocations, not counting the
+/// guard pages on either side.
+const HEAP_SIZE: usize = 64 * 1024 * 1024; // 64 MiB
+
+struct Heap {
+    base: *mut u8,
+    end: *mut u8,
+    bump: *mut u8,
+    high_water: usize,
+}
+
+// `Heap` is only ever touched through `HEAP`'s mutex.
+unsafe impl Send for Heap {}
+
+static HEAP: Mutex<Option<Heap>> = Mutex::new(None);
+
+impl Heap {
+    fn init() -> Heap {
+        let page = page_size();
+        let reserved = HEAP_SIZE + 2 * page;
+
+        unsafe {
+            let region = libc::mmap(
+                std::ptr::null_mut(),
+                reserved,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(region, libc::MAP_FAILED, "failed to reserve the Scheme heap");
+
+            let base = (region as *mut u8).add(page);
+            let made_writable = libc::mprotect(
+                base as *mut libc::c_void,
+                HEAP_SIZE,
+                libc::PROT_READ | libc::PROT_WRITE,
+            );
+            assert_eq!(made_writable, 0, "failed to make the Scheme heap writable");
+
+            Heap { base, end: base.add(HEAP_SIZE), bump: base, high_water: 0 }
+        }
+    }
+
+    /// Bump-allocate `bytes`, 8-byte aligned to match the tag bits stolen
+    /// from the low end of a heap pointer elsewhere in `immediate`.
+    fn alloc(&mut self, bytes: usize) -> *mut u8 {
+        let bytes = align8(bytes);
+
+        if exhausted(self.bump, self.end, bytes) {
+            eprintln!(
+                "error: Scheme heap exhausted ({} bytes requested, {} bytes in use)",
+                bytes, self.high_water
+            );
+            std::process::exit(1);
+        }
+
+        let ptr = self.bump;
+        self.bump = unsafe { self.bump.add(bytes) };
+        self.high_water = unsafe { self.bump.offset_from(self.base) as usize };
+        ptr
+    }
+}
+
+fn align8(bytes: usize) -> usize {
+    (bytes + 7) & !7
+}
+
+/// Would bumping `bump` by `bytes` run past `end`?
+fn exhausted(bump: *mut u8, end: *mut u8, bytes: usize) -> bool {
+    unsafe { bump.add(bytes) > end }
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Bump-allocate `bytes` on the Scheme heap and return the raw pointer.
+///
+/// Callers are responsible for tagging the returned pointer (`ptr | STR`,
+/// `ptr | FLOAT`, ...) -- this only owns the memory, not the object layout
+/// living in it.
+#[no_mangle]
+pub extern "C" fn rt_alloc(bytes: i64) -> *mut u8 {
+    let mut guard = HEAP.lock().unwrap();
+    let heap = guard.get_or_insert_with(Heap::init);
+    heap.alloc(bytes as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_up_to_8_byte_alignment() {
+        assert_eq!(align8(0), 0);
+        assert_eq!(align8(1), 8);
+        assert_eq!(align8(8), 8);
+        assert_eq!(align8(9), 16);
+    }
+
+    #[test]
+    fn rt_alloc_returns_distinct_aligned_pointers_that_advance_the_bump() {
+        // `HEAP` is one process-global bump pointer, and `cargo test` runs
+        // tests (including the `rt_box_float`/`rt_read` ones elsewhere
+        // that also call `rt_alloc`) in parallel, so another thread's
+        // allocation can land between `a` and `b`. Assert only what this
+        // test can actually guarantee: both are 8-byte aligned and `b`
+        // comes no earlier than right after `a`'s (rounded up) allocation.
+        let a = rt_alloc(3) as usize;
+        let b = rt_alloc(8) as usize;
+
+        assert_eq!(a % 8, 0);
+        assert_eq!(b % 8, 0);
+        assert!(b >= a + 8);
+    }
+
+    #[test]
+    fn exhausted_flags_an_allocation_that_would_run_past_end() {
+        let base = 0x1000 as *mut u8;
+        let end = unsafe { base.add(16) };
+
+        assert!(!exhausted(base, end, 16));
+        assert!(exhausted(base, end, 17));
+    }
+}
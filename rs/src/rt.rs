@@ -71,6 +71,7 @@ pub extern "C" fn print(val: i64, nested: bool) {
         }
         STR => print!("\"{}\"", str_str(val)),
         SYM => print!("'{}", sym_name(val)),
+        FLOAT => print_float(float_unbox(val)),
 
         // TODO: Pretty print ports differently from other vectors
         // Example: #<input/output port stdin/out> | #<output port /tmp/foo.txt>
@@ -95,6 +96,22 @@ pub extern "C" fn print(val: i64, nested: bool) {
     std::io::stdout().flush().unwrap();
 }
 
+/// Shared division-error handler emitted code jumps to instead of letting
+/// `idiv` raise a CPU `#DE` on a zero divisor.
+///
+/// Every `(quotient ...)`/`(remainder ...)` call site branches here when its
+/// divisor is zero (see `backend::x86::guard_div`, which also covers why
+/// `INT_MIN / -1` needs no check of its own), so this is the
+/// one place that needs to know how to report the failure: write a
+/// diagnostic to the current error port and exit with a nonzero code,
+/// turning what used to be an uncatchable signal into a well-defined
+/// runtime condition.
+#[no_mangle]
+pub extern "C" fn rt_div_error() -> ! {
+    eprintln!("error: division by zero or arithmetic overflow");
+    std::process::exit(1);
+}
+
 #[no_mangle]
 pub extern "C" fn car(val: i64) -> i64 {
     assert!((val & MASK) == PAIR);
@@ -126,6 +143,42 @@ pub extern "C" fn symbol_eq(a: i64, b: i64) -> i64 {
     }
 }
 
+/// Box `bits` (an `f64`'s bit pattern, reinterpreted as `i64`) onto the
+/// heap and return the `FLOAT`-tagged pointer. Called from the emitted
+/// arithmetic primitives in `backend::x86::float_dispatch`, which moves an
+/// SSE op's `xmm0` result into `rdi` before calling here.
+#[no_mangle]
+pub extern "C" fn rt_box_float(bits: i64) -> i64 {
+    let ptr = crate::heap::rt_alloc(8) as *mut i64;
+
+    unsafe { std::ptr::write(ptr, bits) };
+
+    ptr as i64 | FLOAT
+}
+
+// Unbox an `f64` out of a flonum object
+fn float_unbox(val: i64) -> f64 {
+    assert!((val & MASK) == FLOAT);
+
+    let bits = unsafe { *((val - FLOAT) as *const i64) };
+    f64::from_bits(bits as u64)
+}
+
+fn print_float(f: f64) {
+    print!("{}", format_float(f));
+}
+
+// Finite flonums always print with a decimal point, even when the value is
+// a whole number (`3.0`, not `3`), to keep them visually distinct from
+// fixnums. Infinities and NaN print as `inf`/`-inf`/`NaN`.
+fn format_float(f: f64) -> String {
+    if f.fract() == 0.0 && f.is_finite() {
+        format!("{:.1}", f)
+    } else {
+        format!("{}", f)
+    }
+}
+
 // Get a string pointer from a string object
 fn str_str(val: i64) -> String {
     assert!((val & MASK) == STR);
@@ -154,11 +207,18 @@ fn vec_nth(val: i64, n: i64) -> i64 {
 }
 
 /// IO Primitives for Inc
+///
+/// A port is just an immediate-encoded OS file descriptor (`fd << SHIFT`,
+/// same as a fixnum). `rt_write`/`rt_read` decode it back into a real `fd`
+/// and do a genuine `write(2)`/`read(2)` against it, rather than (as they
+/// used to) ignoring the fd and re-opening a path stashed in the port
+/// object -- which meant every `rt_write` truncated the file from scratch.
 pub mod io {
     use super::*;
     use std::{
-        fs::{self, File},
-        os::unix::io::AsRawFd,
+        fs::File,
+        io::Read,
+        os::unix::io::{FromRawFd, IntoRawFd, RawFd},
     };
 
     // Standard ports can be overridden in Scheme, but these constants would do
@@ -182,7 +242,10 @@ pub mod io {
     /// Creates file if it doesn't exist already
     #[no_mangle]
     pub extern "C" fn rt_open_write(fname: i64) -> i64 {
-        let f = File::create(str_str(fname)).unwrap().as_raw_fd();
+        // `into_raw_fd` hands ownership of the fd to the caller instead of
+        // closing it when the `File` is dropped -- the port needs it to
+        // stay open for later `rt_write` calls.
+        let f = File::create(str_str(fname)).unwrap().into_raw_fd();
         i64::from(f << SHIFT)
     }
 
@@ -190,57 +253,137 @@ pub mod io {
     /// Fails if file doesn't exist already
     #[no_mangle]
     pub extern "C" fn rt_open_read(fname: i64) -> i64 {
-        let f = File::open(str_str(fname)).unwrap().as_raw_fd();
+        let f = File::open(str_str(fname)).unwrap().into_raw_fd();
         i64::from(f << SHIFT)
     }
 
+    /// Decode the OS fd a port was built from, without taking ownership of
+    /// it -- the caller is expected to `mem::forget` the `File` it wraps
+    /// the fd in once done, so repeated writes/reads to the same port (or
+    /// to a standard stream we don't own) don't close it early.
+    fn port_fd(port: i64) -> RawFd {
+        (port >> SHIFT) as RawFd
+    }
+
     /// Write a string object to a port
     #[no_mangle]
     pub extern "C" fn rt_write(data: i64, port: i64) -> i64 {
-        let path = str_str(vec_nth(port, 1));
-        fs::write(&path, str_str(data)).unwrap_or_else(|_| panic!("Failed to write to {}", &path));
+        let fd = port_fd(port);
+        let bytes = str_str(data).into_bytes();
+
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        let result = file.write_all(&bytes);
+        std::mem::forget(file);
+        result.unwrap_or_else(|_| panic!("Failed to write to fd {}", fd));
 
         NIL
     }
 
     /// Read string from a port object
-    //
-    // ⚠️ This is so far away from the spec and should be called something else.
-    //
-    // This is honestly making me wonder WTH I'm really doing. There is no need
-    // to really do this in assembly, what I need is a custom allocator in Rust.
-    // See `strings::make` as well.
-    //
-    // This is legit cursed!
     #[no_mangle]
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     pub extern "C" fn rt_read(port: i64) -> i64 {
-        let path = str_str(vec_nth(port, 1));
-        let data = fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {:?}", &path, e));
+        let fd = port_fd(port);
 
-        let r12: u64;
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        let mut data = Vec::new();
+        let result = file.read_to_end(&mut data);
+        std::mem::forget(file);
+        result.unwrap_or_else(|e| panic!("Failed to read fd {}: {:?}", fd, e));
 
-        unsafe {
-            // Read current heap pointer from r12
-            asm!("nop" : "={r12}"(r12) ::: "intel");
-        }
-
-        let heap = r12 as *mut usize;
-        let str = (r12 + 8) as *mut u8;
+        // Layout matches every other `STR` object: a length prefix word
+        // followed by the bytes themselves.
+        let heap = crate::heap::rt_alloc(WORDSIZE as i64 + data.len() as i64) as *mut usize;
+        let str = unsafe { (heap as *mut u8).add(WORDSIZE as usize) };
 
         unsafe {
-            // Increment r12 to allocate space
-            asm!("add r12, $0" :: "m"(data.len()) :: "intel");
-
-            //TODO: Understand why this is not `*heap = data.len();`
-            // Write prefix length
             std::ptr::write(heap, data.len());
-
-            // Write data
             std::ptr::copy(data.as_ptr(), str, data.len());
         }
 
         // Return immediate encoded string object
         heap as i64 | STR
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::fs;
+
+        // Build a minimal `STR` object good enough for `str_str`: a length
+        // prefix word followed by the nul-terminated bytes, allocated on
+        // the (8-byte aligned, never freed) Scheme heap so it can be tagged.
+        fn str_obj(s: &str) -> i64 {
+            let obj = crate::heap::rt_alloc(WORDSIZE as i64 + s.len() as i64 + 1);
+
+            unsafe {
+                std::ptr::write(obj as *mut usize, s.len());
+                let bytes = obj.add(WORDSIZE as usize);
+                std::ptr::copy(s.as_ptr(), bytes, s.len());
+                std::ptr::write(bytes.add(s.len()), 0);
+            }
+
+            obj as i64 | STR
+        }
+
+        fn temp_path(name: &str) -> std::path::PathBuf {
+            std::env::temp_dir().join(format!("inc-rt-test-{}-{}", std::process::id(), name))
+        }
+
+        #[test]
+        fn rt_write_keeps_the_fd_open_across_calls_instead_of_truncating() {
+            let path = temp_path("write.txt");
+            let port = rt_open_write(str_obj(path.to_str().unwrap()));
+
+            rt_write(str_obj("hello "), port);
+            rt_write(str_obj("world"), port);
+
+            let contents = fs::read_to_string(&path).unwrap();
+            let _ = fs::remove_file(&path);
+
+            assert_eq!(contents, "hello world");
+        }
+
+        #[test]
+        fn rt_read_reads_the_fd_not_a_path_stashed_on_the_port() {
+            let path = temp_path("read.txt");
+            fs::write(&path, "roundtrip").unwrap();
+
+            let port = rt_open_read(str_obj(path.to_str().unwrap()));
+            let result = rt_read(port);
+            let _ = fs::remove_file(&path);
+
+            assert_eq!(result & MASK, STR);
+            assert_eq!(string_length(result), n(9) as usize);
+
+            let data = unsafe {
+                std::slice::from_raw_parts((result - STR + WORDSIZE as i64) as *const u8, 9)
+            };
+            assert_eq!(data, b"roundtrip");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flonum_round_trips_through_box_and_unbox() {
+        let bits = 3.5f64.to_bits() as i64;
+        let boxed = rt_box_float(bits);
+
+        assert_eq!(boxed & MASK, FLOAT);
+        assert_eq!(float_unbox(boxed), 3.5);
+    }
+
+    #[test]
+    fn whole_numbers_format_with_a_decimal_point() {
+        assert_eq!(format_float(3.0), "3.0");
+        assert_eq!(format_float(-2.0), "-2.0");
+    }
+
+    #[test]
+    fn fractional_numbers_format_without_padding() {
+        assert_eq!(format_float(3.5), "3.5");
+    }
 }
\ No newline at end of file